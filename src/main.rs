@@ -1,99 +1,166 @@
+use std::collections::HashMap;
 use std::io::prelude::*;
+use std::net::{SocketAddr, TcpListener, TcpStream, UdpSocket};
 use std::process::ExitCode;
-use std::{env, io, time};
+use std::sync::{Arc, Mutex};
+use std::{env, io, thread, time};
 
 const STATS_INTERVAL: u64 = 10 * 1000;
+const DEFAULT_BLOCK_SIZE: usize = 1 << 16;
 
 fn main() -> io::Result<ExitCode> {
     let mut args = env::args();
     let program = args.next();
-    if let Some(arg) = args.next() {
-        let usage = format!(
-            "Usage: any-command-that-prints-identifiers-infinitely | {}",
-            program.as_deref().unwrap_or("scru64-test")
-        );
-        return if arg == "-h" || arg == "--help" {
+    let usage = format!(
+        "Usage: any-command-that-prints-identifiers-infinitely | {} [--node-id-size N] [--listen ADDR] [--format text|json] [--block-size BYTES]",
+        program.as_deref().unwrap_or("scru64-test")
+    );
+
+    let mut node_id_size = None;
+    let mut listen_addr = None;
+    let mut format = Format::default();
+    let mut block_size = DEFAULT_BLOCK_SIZE;
+    while let Some(arg) = args.next() {
+        if arg == "-h" || arg == "--help" {
             println!("{usage}");
-            Ok(ExitCode::SUCCESS)
+            return Ok(ExitCode::SUCCESS);
+        } else if arg == "--node-id-size" {
+            let Some(value) = args.next() else {
+                eprintln!("Error: --node-id-size requires a value");
+                eprintln!("{usage}");
+                return Ok(ExitCode::FAILURE);
+            };
+            match parse_node_id_size(&value) {
+                Some(n) => node_id_size = Some(n),
+                None => {
+                    eprintln!("Error: --node-id-size must be an integer between 1 and 23");
+                    eprintln!("{usage}");
+                    return Ok(ExitCode::FAILURE);
+                }
+            }
+        } else if let Some(value) = arg.strip_prefix("--node-id-size=") {
+            match parse_node_id_size(value) {
+                Some(n) => node_id_size = Some(n),
+                None => {
+                    eprintln!("Error: --node-id-size must be an integer between 1 and 23");
+                    eprintln!("{usage}");
+                    return Ok(ExitCode::FAILURE);
+                }
+            }
+        } else if arg == "--listen" {
+            let Some(value) = args.next() else {
+                eprintln!("Error: --listen requires an ADDR value");
+                eprintln!("{usage}");
+                return Ok(ExitCode::FAILURE);
+            };
+            listen_addr = Some(value);
+        } else if let Some(value) = arg.strip_prefix("--listen=") {
+            listen_addr = Some(value.to_owned());
+        } else if arg == "--format" {
+            let Some(value) = args.next() else {
+                eprintln!("Error: --format requires a value");
+                eprintln!("{usage}");
+                return Ok(ExitCode::FAILURE);
+            };
+            match parse_format(&value) {
+                Some(f) => format = f,
+                None => {
+                    eprintln!("Error: --format must be \"text\" or \"json\"");
+                    eprintln!("{usage}");
+                    return Ok(ExitCode::FAILURE);
+                }
+            }
+        } else if let Some(value) = arg.strip_prefix("--format=") {
+            match parse_format(value) {
+                Some(f) => format = f,
+                None => {
+                    eprintln!("Error: --format must be \"text\" or \"json\"");
+                    eprintln!("{usage}");
+                    return Ok(ExitCode::FAILURE);
+                }
+            }
+        } else if arg == "--block-size" {
+            let Some(value) = args.next() else {
+                eprintln!("Error: --block-size requires a value");
+                eprintln!("{usage}");
+                return Ok(ExitCode::FAILURE);
+            };
+            match value.parse() {
+                Ok(n) if n > 0 => block_size = n,
+                _ => {
+                    eprintln!("Error: --block-size must be a positive integer");
+                    eprintln!("{usage}");
+                    return Ok(ExitCode::FAILURE);
+                }
+            }
+        } else if let Some(value) = arg.strip_prefix("--block-size=") {
+            match value.parse() {
+                Ok(n) if n > 0 => block_size = n,
+                _ => {
+                    eprintln!("Error: --block-size must be a positive integer");
+                    eprintln!("{usage}");
+                    return Ok(ExitCode::FAILURE);
+                }
+            }
         } else {
             eprintln!("Error: unknown argument: {arg}");
             eprintln!("{usage}");
-            Ok(ExitCode::FAILURE)
-        };
+            return Ok(ExitCode::FAILURE);
+        }
     }
 
-    let mut reader = io::stdin().lock();
-    let mut buffer = Vec::with_capacity(16);
-    println!(
-        "Reading IDs from stdin and will show stats every {} seconds. Press Ctrl-C to quit.",
-        STATS_INTERVAL / 1000
-    );
-
-    let mut st = Status::default();
-    let mut prev = Identifier::default();
-    while {
-        buffer.clear();
-        reader.read_until(b'\n', &mut buffer)? > 0
-    } {
-        let line = match buffer.strip_suffix(b"\n") {
-            Some(s) => s.strip_suffix(b"\r").unwrap_or(s),
-            None => &buffer,
-        };
+    let shared = Arc::new(Mutex::new(Status::new(node_id_size, format)));
 
-        let Some(e) = Identifier::new(line) else {
-            eprintln!("Error: invalid string representation");
-            st.n_errors += 1;
-            continue;
-        };
+    match listen_addr {
+        None => {
+            if format == Format::Text {
+                println!(
+                    "Reading IDs from stdin and will show stats every {} seconds. Press Ctrl-C to quit.",
+                    STATS_INTERVAL / 1000
+                );
+            }
 
-        st.n_processed += 1;
-        if e.str_bytes <= prev.str_bytes {
-            eprintln!("Error: string representation not monotonically ordered");
-            st.n_errors += 1;
-            continue;
-        }
-        if e.int_value <= prev.int_value {
-            eprintln!("Error: integer representation not monotonically ordered");
-            st.n_errors += 1;
-            continue;
-        }
-        if e.unix_ts_ms < prev.unix_ts_ms {
-            eprintln!("Error: clock went backwards");
-            st.n_errors += 1;
-            continue;
-        } else if e.unix_ts_ms == prev.unix_ts_ms && e.node_ctr < prev.node_ctr {
-            eprintln!("Error: node_ctr went backwards within same timestamp");
-            st.n_errors += 1;
-            continue;
+            let mut reader = BlockReader::new(io::stdin().lock(), block_size);
+            let mut stream = StreamState::default();
+            reader.for_each_line(|line| {
+                process_id(line, node_id_size, false, &mut stream, &shared)
+            })?;
         }
+        Some(addr) => {
+            if format == Format::Text {
+                println!(
+                    "Listening for IDs on {addr} (TCP and UDP) and will show stats every {} seconds. Press Ctrl-C to quit.",
+                    STATS_INTERVAL / 1000
+                );
+            }
 
-        // Triggered per line
-        if st.ts_first == 0 {
-            st.ts_first = e.unix_ts_ms;
-        }
-        st.ts_last = e.unix_ts_ms;
+            let tcp_listener = TcpListener::bind(&addr)?;
+            let udp_socket = UdpSocket::bind(&addr)?;
 
-        // Triggered per 256 millisecond or at node_ctr increment
-        if e.node_ctr != prev.node_ctr + 1 {
-            if st.ts_last_counter_update > 0 {
-                st.n_counter_lo_update += 1;
-                st.sum_intervals_counter_update += e.unix_ts_ms - st.ts_last_counter_update;
-            }
-            st.ts_last_counter_update = e.unix_ts_ms;
-        }
+            let tcp_shared = Arc::clone(&shared);
+            let tcp_handle = thread::spawn(move || {
+                for conn in tcp_listener.incoming() {
+                    match conn {
+                        Ok(conn) => {
+                            let shared = Arc::clone(&tcp_shared);
+                            thread::spawn(move || {
+                                handle_tcp_connection(conn, node_id_size, block_size, shared)
+                            });
+                        }
+                        Err(e) => eprintln!("Error: failed to accept TCP connection: {e}"),
+                    }
+                }
+            });
 
-        // Triggered per STATS_INTERVAL seconds
-        if e.unix_ts_ms > st.ts_last_stats_print + STATS_INTERVAL {
-            if st.ts_last_stats_print > 0 {
-                st.print()?;
-            }
-            st.ts_last_stats_print = e.unix_ts_ms;
-        }
+            let udp_shared = Arc::clone(&shared);
+            let udp_handle = thread::spawn(move || handle_udp_socket(udp_socket, node_id_size, udp_shared));
 
-        // Prepare for next loop
-        prev = e;
+            tcp_handle.join().expect("TCP accept thread panicked");
+            udp_handle.join().expect("UDP receive thread panicked");
+        }
     }
 
+    let st = shared.lock().unwrap();
     if st.n_processed > 0 {
         st.print()?;
     } else {
@@ -108,6 +175,489 @@ fn main() -> io::Result<ExitCode> {
     }
 }
 
+/// Parses the value of `--node-id-size`, an integer between 1 and 23.
+fn parse_node_id_size(value: &str) -> Option<u8> {
+    let n: u8 = value.parse().ok()?;
+    (1..=23).contains(&n).then_some(n)
+}
+
+/// Splits a 24-bit `node_ctr` field into `node_id` and `counter` at a width of `node_id_size` bits.
+fn decompose_node_ctr(node_ctr: u32, node_id_size: u8) -> (u32, u32) {
+    let counter_bits = 24 - u32::from(node_id_size);
+    let node_id = node_ctr >> counter_bits;
+    let counter = node_ctr & ((1u32 << counter_bits) - 1);
+    (node_id, counter)
+}
+
+/// Output format for stats and validation errors.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+enum Format {
+    #[default]
+    Text,
+    Json,
+}
+
+fn parse_format(value: &str) -> Option<Format> {
+    match value {
+        "text" => Some(Format::Text),
+        "json" => Some(Format::Json),
+        _ => None,
+    }
+}
+
+/// The kind of a validation failure, shared between the human-readable and JSON error reports.
+#[derive(Debug, Clone, Copy)]
+enum ErrorKind {
+    InvalidRepresentation,
+    StringNotMonotonic,
+    IntNotMonotonic,
+    ClockWentBackwards,
+    NodeCtrWentBackwards,
+    CounterDidNotIncrease,
+    NodeIdChangedWithinStream,
+}
+
+impl ErrorKind {
+    fn message(self) -> &'static str {
+        match self {
+            Self::InvalidRepresentation => "invalid string representation",
+            Self::StringNotMonotonic => "string representation not monotonically ordered",
+            Self::IntNotMonotonic => "integer representation not monotonically ordered",
+            Self::ClockWentBackwards => "clock went backwards",
+            Self::NodeCtrWentBackwards => "node_ctr went backwards within same timestamp",
+            Self::CounterDidNotIncrease => "counter did not increase within timestamp",
+            Self::NodeIdChangedWithinStream => "node_id changed within a single stream",
+        }
+    }
+
+    fn tag(self) -> &'static str {
+        match self {
+            Self::InvalidRepresentation => "invalid_representation",
+            Self::StringNotMonotonic => "string_not_monotonic",
+            Self::IntNotMonotonic => "int_not_monotonic",
+            Self::ClockWentBackwards => "clock_went_backwards",
+            Self::NodeCtrWentBackwards => "node_ctr_went_backwards",
+            Self::CounterDidNotIncrease => "counter_did_not_increase",
+            Self::NodeIdChangedWithinStream => "node_id_changed_within_stream",
+        }
+    }
+}
+
+/// Builds the JSON error record emitted by [`report_error`] under `--format json`.
+fn json_error_line(
+    kind: ErrorKind,
+    line: &[u8],
+    decoded: Option<&Identifier>,
+    prev: Option<&Identifier>,
+    node_id: Option<u32>,
+) -> String {
+    let decoded_fields = match decoded {
+        Some(e) => format!(
+            "\"int_value\":{},\"unix_ts_ms\":{},\"node_ctr\":{}",
+            e.int_value, e.unix_ts_ms, e.node_ctr
+        ),
+        None => "\"int_value\":null,\"unix_ts_ms\":null,\"node_ctr\":null".to_owned(),
+    };
+    let prev_field = match prev {
+        Some(p) => format!(
+            "{{\"line\":\"{}\",\"int_value\":{},\"unix_ts_ms\":{},\"node_ctr\":{}}}",
+            json_escape(&p.str_bytes),
+            p.int_value,
+            p.unix_ts_ms,
+            p.node_ctr
+        ),
+        None => "null".to_owned(),
+    };
+    let node_id_field = node_id.map_or("null".to_owned(), |n| n.to_string());
+    format!(
+        "{{\"error\":\"{}\",\"line\":\"{}\",{decoded_fields},\"prev\":{prev_field},\"node_id\":{node_id_field}}}",
+        kind.tag(),
+        json_escape(line),
+    )
+}
+
+fn report_error(
+    format: Format,
+    kind: ErrorKind,
+    line: &[u8],
+    decoded: Option<&Identifier>,
+    prev: Option<&Identifier>,
+    node_id: Option<u32>,
+) {
+    match format {
+        Format::Text => match node_id {
+            Some(node_id) => eprintln!("Error: {} (node_id={node_id})", kind.message()),
+            None => eprintln!("Error: {}", kind.message()),
+        },
+        Format::Json => eprintln!("{}", json_error_line(kind, line, decoded, prev, node_id)),
+    }
+}
+
+/// Escapes a raw byte slice for embedding in a JSON string.
+fn json_escape(bytes: &[u8]) -> String {
+    let mut out = String::with_capacity(bytes.len());
+    for &b in bytes {
+        match b {
+            b'"' => out.push_str("\\\""),
+            b'\\' => out.push_str("\\\\"),
+            0x20..=0x7e => out.push(b as char),
+            _ => out.push_str(&format!("\\u{b:04x}")),
+        }
+    }
+    out
+}
+
+/// Strips the trailing `\n` (or `\r\n`) delimiter off a raw input chunk.
+fn strip_line(buffer: &[u8]) -> &[u8] {
+    match buffer.strip_suffix(b"\n") {
+        Some(s) => s.strip_suffix(b"\r").unwrap_or(s),
+        None => buffer,
+    }
+}
+
+/// Reads newline-delimited tokens out of a `Read` in large fixed-size blocks instead of one `read_until` syscall per line.
+struct BlockReader<R> {
+    reader: R,
+    block: Vec<u8>,
+    pos: usize,
+    filled: usize,
+    carry: Vec<u8>,
+}
+
+impl<R: Read> BlockReader<R> {
+    fn new(reader: R, block_size: usize) -> Self {
+        Self {
+            reader,
+            block: vec![0u8; block_size.max(1)],
+            pos: 0,
+            filled: 0,
+            carry: Vec::new(),
+        }
+    }
+
+    /// Invokes `f` once per line (with the `\n`/`\r\n` delimiter stripped) until EOF.
+    fn for_each_line(&mut self, mut f: impl FnMut(&[u8]) -> io::Result<()>) -> io::Result<()> {
+        loop {
+            if self.pos >= self.filled {
+                self.filled = self.reader.read(&mut self.block)?;
+                self.pos = 0;
+                if self.filled == 0 {
+                    if !self.carry.is_empty() {
+                        f(strip_cr(&self.carry))?;
+                        self.carry.clear();
+                    }
+                    return Ok(());
+                }
+            }
+
+            let remaining = &self.block[self.pos..self.filled];
+            match find_newline(remaining) {
+                Some(offset) => {
+                    let chunk = &remaining[..offset];
+                    self.pos += offset + 1;
+                    if self.carry.is_empty() {
+                        f(strip_cr(chunk))?;
+                    } else {
+                        self.carry.extend_from_slice(chunk);
+                        f(strip_cr(&self.carry))?;
+                        self.carry.clear();
+                    }
+                }
+                None => {
+                    self.carry.extend_from_slice(remaining);
+                    self.pos = self.filled;
+                }
+            }
+        }
+    }
+}
+
+/// Strips a trailing `\r` off a chunk that has already had its `\n` delimiter removed.
+fn strip_cr(chunk: &[u8]) -> &[u8] {
+    chunk.strip_suffix(b"\r").unwrap_or(chunk)
+}
+
+/// Linear (memchr-style) scan for the first `\n` in `haystack`.
+fn find_newline(haystack: &[u8]) -> Option<usize> {
+    haystack.iter().position(|&b| b == b'\n')
+}
+
+#[cfg(test)]
+mod block_reader_tests {
+    use super::*;
+    use std::io::Cursor;
+
+    fn collect_lines(data: &[u8], block_size: usize) -> Vec<Vec<u8>> {
+        let mut reader = BlockReader::new(Cursor::new(data.to_vec()), block_size);
+        let mut lines = Vec::new();
+        reader
+            .for_each_line(|line| {
+                lines.push(line.to_vec());
+                Ok(())
+            })
+            .unwrap();
+        lines
+    }
+
+    #[test]
+    fn splits_lines_within_a_single_block() {
+        let lines = collect_lines(b"aaa\nbbb\nccc\n", 64);
+        assert_eq!(lines, vec![b"aaa".to_vec(), b"bbb".to_vec(), b"ccc".to_vec()]);
+    }
+
+    #[test]
+    fn reassembles_a_line_straddling_a_block_boundary() {
+        // A 3-byte block forces every line above to span more than one read().
+        let lines = collect_lines(b"aaa\nbbb\nccc", 3);
+        assert_eq!(lines, vec![b"aaa".to_vec(), b"bbb".to_vec(), b"ccc".to_vec()]);
+    }
+
+    #[test]
+    fn tolerates_crlf_line_endings() {
+        let lines = collect_lines(b"aaa\r\nbbb\r\nccc\r\n", 3);
+        assert_eq!(lines, vec![b"aaa".to_vec(), b"bbb".to_vec(), b"ccc".to_vec()]);
+    }
+
+    #[test]
+    fn emits_a_final_line_with_no_trailing_newline() {
+        let lines = collect_lines(b"aaa\nbbb", 2);
+        assert_eq!(lines, vec![b"aaa".to_vec(), b"bbb".to_vec()]);
+    }
+
+    #[test]
+    fn empty_input_yields_no_lines() {
+        assert!(collect_lines(b"", 64).is_empty());
+    }
+
+    /// Wall-clock comparison against the old one-`read_until`-per-line path; run with `--ignored`.
+    #[test]
+    #[ignore]
+    fn compares_block_reader_with_read_until_per_line() {
+        let mut data = Vec::new();
+        for i in 0..2_000_000u64 {
+            data.extend_from_slice(format!("{i:012}\n").as_bytes());
+        }
+
+        let start = std::time::Instant::now();
+        let mut n = 0u64;
+        BlockReader::new(Cursor::new(data.clone()), DEFAULT_BLOCK_SIZE)
+            .for_each_line(|_| {
+                n += 1;
+                Ok(())
+            })
+            .unwrap();
+        let block_elapsed = start.elapsed();
+        assert_eq!(n, 2_000_000);
+
+        let start = std::time::Instant::now();
+        let mut reader = Cursor::new(data);
+        let mut buffer = Vec::new();
+        let mut n = 0u64;
+        while {
+            buffer.clear();
+            reader.read_until(b'\n', &mut buffer).unwrap() > 0
+        } {
+            n += 1;
+        }
+        let per_line_elapsed = start.elapsed();
+        assert_eq!(n, 2_000_000);
+
+        println!(
+            "block reader: {block_elapsed:?} ({:.1} IDs/us), per-line read_until: {per_line_elapsed:?} ({:.1} IDs/us)",
+            2_000_000.0 / block_elapsed.as_micros().max(1) as f64,
+            2_000_000.0 / per_line_elapsed.as_micros().max(1) as f64,
+        );
+    }
+}
+
+/// Per-stream state for one independent input stream (stdin, one TCP connection, or one UDP source address).
+#[derive(Debug, Default)]
+struct StreamState {
+    prev: Identifier,
+    ts_last_counter_update: u64,
+    claimed_node_id: Option<u32>,
+}
+
+/// Decodes and validates one ID line. `enforce_node_id` requires `node_id` to stay constant within
+/// `stream`, which holds for TCP/UDP but not stdin, which interleaves several nodes by design.
+fn process_id(
+    line: &[u8],
+    node_id_size: Option<u8>,
+    enforce_node_id: bool,
+    stream: &mut StreamState,
+    shared: &Mutex<Status>,
+) -> io::Result<()> {
+    let decoded = Identifier::new(line);
+
+    let mut st = shared.lock().unwrap();
+    let format = st.format;
+
+    let Some(e) = decoded else {
+        report_error(format, ErrorKind::InvalidRepresentation, line, None, None, None);
+        st.n_errors += 1;
+        return Ok(());
+    };
+
+    st.n_processed += 1;
+
+    let mut valid = true;
+    if let Some(node_id_size) = node_id_size {
+        let (node_id, counter) = decompose_node_ctr(e.node_ctr, node_id_size);
+
+        if enforce_node_id {
+            match stream.claimed_node_id {
+                Some(claimed) if claimed != node_id => {
+                    report_error(
+                        format,
+                        ErrorKind::NodeIdChangedWithinStream,
+                        line,
+                        Some(&e),
+                        None,
+                        Some(node_id),
+                    );
+                    st.n_errors += 1;
+                    valid = false;
+                }
+                _ => stream.claimed_node_id = Some(node_id),
+            }
+        }
+
+        if valid {
+            valid = match st.validate_node(node_id, counter, &e) {
+                Ok(()) => true,
+                Err((kind, prev)) => {
+                    report_error(format, kind, line, Some(&e), Some(&prev), Some(node_id));
+                    st.n_errors += 1;
+                    false
+                }
+            };
+        }
+    } else if e.str_bytes <= stream.prev.str_bytes {
+        report_error(
+            format,
+            ErrorKind::StringNotMonotonic,
+            line,
+            Some(&e),
+            Some(&stream.prev),
+            None,
+        );
+        st.n_errors += 1;
+        valid = false;
+    } else if e.int_value <= stream.prev.int_value {
+        report_error(
+            format,
+            ErrorKind::IntNotMonotonic,
+            line,
+            Some(&e),
+            Some(&stream.prev),
+            None,
+        );
+        st.n_errors += 1;
+        valid = false;
+    } else if e.unix_ts_ms < stream.prev.unix_ts_ms {
+        report_error(
+            format,
+            ErrorKind::ClockWentBackwards,
+            line,
+            Some(&e),
+            Some(&stream.prev),
+            None,
+        );
+        st.n_errors += 1;
+        valid = false;
+    } else if e.unix_ts_ms == stream.prev.unix_ts_ms && e.node_ctr < stream.prev.node_ctr {
+        report_error(
+            format,
+            ErrorKind::NodeCtrWentBackwards,
+            line,
+            Some(&e),
+            Some(&stream.prev),
+            None,
+        );
+        st.n_errors += 1;
+        valid = false;
+    }
+
+    if !valid {
+        return Ok(());
+    }
+
+    // Triggered per 256 millisecond or at node_ctr increment
+    if node_id_size.is_none() && e.node_ctr != stream.prev.node_ctr + 1 {
+        if stream.ts_last_counter_update > 0 {
+            st.n_counter_lo_update += 1;
+            st.sum_intervals_counter_update += e.unix_ts_ms - stream.ts_last_counter_update;
+        }
+        stream.ts_last_counter_update = e.unix_ts_ms;
+    }
+
+    // Triggered per line
+    if st.ts_first == 0 {
+        st.ts_first = e.unix_ts_ms;
+    }
+    st.ts_last = e.unix_ts_ms;
+
+    // Triggered per STATS_INTERVAL seconds
+    if e.unix_ts_ms > st.ts_last_stats_print + STATS_INTERVAL {
+        if st.ts_last_stats_print > 0 {
+            st.print()?;
+        }
+        st.ts_last_stats_print = e.unix_ts_ms;
+    }
+
+    stream.prev = e;
+    Ok(())
+}
+
+/// Reads line-delimited IDs from one accepted TCP connection as its own ordered stream.
+fn handle_tcp_connection(
+    conn: TcpStream,
+    node_id_size: Option<u8>,
+    block_size: usize,
+    shared: Arc<Mutex<Status>>,
+) {
+    let mut reader = BlockReader::new(conn, block_size);
+    let mut stream = StreamState::default();
+    if let Err(e) =
+        reader.for_each_line(|line| process_id(line, node_id_size, true, &mut stream, &shared))
+    {
+        eprintln!("Error: TCP read failed: {e}");
+    }
+}
+
+/// Reads one ID per UDP datagram, treating each distinct source address as its own ordered stream.
+fn handle_udp_socket(socket: UdpSocket, node_id_size: Option<u8>, shared: Arc<Mutex<Status>>) {
+    let mut streams: HashMap<SocketAddr, StreamState> = HashMap::new();
+    let mut buf = [0u8; 16];
+    loop {
+        let (n, src) = match socket.recv_from(&mut buf) {
+            Ok(result) => result,
+            Err(e) => {
+                eprintln!("Error: UDP receive failed: {e}");
+                return;
+            }
+        };
+        let stream = streams.entry(src).or_default();
+        if let Err(e) = process_id(strip_line(&buf[..n]), node_id_size, true, stream, &shared) {
+            eprintln!("Error: failed to report stats: {e}");
+            return;
+        }
+    }
+}
+
+/// Per-node validation and interval-tracking state used when `--node-id-size` is set.
+#[derive(Debug, Default)]
+struct NodeState {
+    prev: Identifier,
+    prev_counter: u32,
+    n_processed: usize,
+    ts_first: u64,
+    ts_last: u64,
+    n_counter_lo_update: usize,
+    ts_last_counter_update: u64,
+    sum_intervals_counter_update: u64,
+}
+
 #[derive(Debug, Default)]
 struct Status {
     n_processed: usize,
@@ -116,17 +666,133 @@ struct Status {
     ts_last: u64,
 
     n_counter_lo_update: usize,
-    ts_last_counter_update: u64,
     sum_intervals_counter_update: u64,
 
     ts_last_stats_print: u64,
+
+    node_id_size: Option<u8>,
+    nodes: HashMap<u32, NodeState>,
+
+    format: Format,
 }
 
 impl Status {
+    fn new(node_id_size: Option<u8>, format: Format) -> Self {
+        Self {
+            node_id_size,
+            format,
+            ..Default::default()
+        }
+    }
+
+    /// Validates `e` against the per-node stream rooted at `node_id`.
+    fn validate_node(
+        &mut self,
+        node_id: u32,
+        counter: u32,
+        e: &Identifier,
+    ) -> Result<(), (ErrorKind, Identifier)> {
+        let ns = self.nodes.entry(node_id).or_default();
+
+        if ns.n_processed > 0 {
+            if e.unix_ts_ms < ns.prev.unix_ts_ms {
+                return Err((ErrorKind::ClockWentBackwards, ns.prev.clone()));
+            } else if e.unix_ts_ms == ns.prev.unix_ts_ms && counter <= ns.prev_counter {
+                return Err((ErrorKind::CounterDidNotIncrease, ns.prev.clone()));
+            }
+
+            // Triggered per 256 millisecond or at counter increment
+            if counter != ns.prev_counter + 1 {
+                if ns.ts_last_counter_update > 0 {
+                    ns.n_counter_lo_update += 1;
+                    ns.sum_intervals_counter_update += e.unix_ts_ms - ns.ts_last_counter_update;
+                }
+                ns.ts_last_counter_update = e.unix_ts_ms;
+            }
+        }
+
+        ns.n_processed += 1;
+        if ns.ts_first == 0 {
+            ns.ts_first = e.unix_ts_ms;
+        }
+        ns.ts_last = e.unix_ts_ms;
+        ns.prev = e.clone();
+        ns.prev_counter = counter;
+
+        Ok(())
+    }
+
     fn print(&self) -> io::Result<()> {
+        match self.format {
+            Format::Text => self.print_text(),
+            Format::Json => self.print_json(),
+        }
+    }
+
+    /// Builds the JSON object emitted by [`Self::print_json`].
+    fn json_line(&self) -> String {
         let time_elapsed = self.ts_last - self.ts_first;
+        let mean_ids_per_256ms = self.n_processed as u64 / (time_elapsed >> 8).max(1);
+        let clock_lag_sec = get_current_time() - (self.ts_last as f64) / 1000.0;
+        let mean_counter_update_interval_ms = if self.node_id_size.is_none() && self.n_counter_lo_update > 0 {
+            format!(
+                "{:.3}",
+                self.sum_intervals_counter_update as f64 / self.n_counter_lo_update as f64
+            )
+        } else {
+            "null".to_owned()
+        };
+
+        format!(
+            "{{\"n_processed\":{},\"n_errors\":{},\"ts_first\":{},\"ts_last\":{},\"mean_ids_per_256ms\":{},\"clock_lag_sec\":{:.3},\"mean_counter_update_interval_ms\":{}}}",
+            self.n_processed,
+            self.n_errors,
+            self.ts_first,
+            self.ts_last,
+            mean_ids_per_256ms,
+            clock_lag_sec,
+            mean_counter_update_interval_ms,
+        )
+    }
 
+    /// Emits one JSON object summarizing the interval.
+    fn print_json(&self) -> io::Result<()> {
+        println!("{}", self.json_line());
+        Ok(())
+    }
+
+    fn print_text(&self) -> io::Result<()> {
         let mut buf = io::stdout().lock();
+
+        if self.node_id_size.is_some() {
+            writeln!(buf)?;
+            writeln!(
+                buf,
+                "{:<10} {:>12} {:>12} {:>12}",
+                "NODE", "N_PROCESSED", "IDS/256MS", "MEAN_CTR_INTERVAL(ms)"
+            )?;
+            let mut node_ids: Vec<&u32> = self.nodes.keys().collect();
+            node_ids.sort();
+            for node_id in node_ids {
+                let ns = &self.nodes[node_id];
+                let time_elapsed = ns.ts_last - ns.ts_first;
+                let ids_per_256ms = if time_elapsed > 0 {
+                    ns.n_processed as u64 / (time_elapsed >> 8).max(1)
+                } else {
+                    0
+                };
+                let mean_ctr_interval = ns.sum_intervals_counter_update as f64
+                    / ns.n_counter_lo_update.max(1) as f64;
+                writeln!(
+                    buf,
+                    "{:<10} {:>12} {:>12} {:>12.3}",
+                    node_id, ns.n_processed, ids_per_256ms, mean_ctr_interval
+                )?;
+            }
+        }
+
+        let time_elapsed = self.ts_last - self.ts_first;
+
         writeln!(buf)?;
         writeln!(buf, "{:<48} {:>12} {:>12}", "STAT", "EXPECTED", "ACTUAL")?;
         writeln!(
@@ -151,7 +817,7 @@ impl Status {
             "{:<48} {:>12} {:>12}",
             "Mean number of IDs per 256 millisecond",
             "<~MAX_CTR/2",
-            self.n_processed as u64 / (time_elapsed >> 8)
+            self.n_processed as u64 / (time_elapsed >> 8).max(1)
         )?;
         writeln!(
             buf,
@@ -160,18 +826,111 @@ impl Status {
             "-10.0 - 0.0",
             get_current_time() - (self.ts_last as f64) / 1000.0
         )?;
-        writeln!(
-            buf,
-            "{:<48} {:>12} {:>12.3}",
-            "Mean interval of counter updates (msec)",
-            "~256",
-            self.sum_intervals_counter_update as f64 / self.n_counter_lo_update as f64
-        )?;
+        if self.node_id_size.is_none() {
+            writeln!(
+                buf,
+                "{:<48} {:>12} {:>12.3}",
+                "Mean interval of counter updates (msec)",
+                "~256",
+                self.sum_intervals_counter_update as f64 / self.n_counter_lo_update as f64
+            )?;
+        }
 
         Ok(())
     }
 }
 
+#[cfg(test)]
+mod node_validation_tests {
+    use super::*;
+
+    fn id(unix_ts_ms: u64, node_ctr: u32) -> Identifier {
+        Identifier {
+            str_bytes: *b"000000000000",
+            int_value: (unix_ts_ms >> 8 << 24) | node_ctr as u64,
+            unix_ts_ms,
+            node_ctr,
+        }
+    }
+
+    #[test]
+    fn decompose_node_ctr_splits_high_bits_as_node_id() {
+        assert_eq!(decompose_node_ctr(0xab_1234, 8), (0xab, 0x1234));
+        assert_eq!(decompose_node_ctr(0x00_0001, 8), (0x00, 0x01));
+    }
+
+    #[test]
+    fn validate_node_accepts_increasing_counter_within_a_timestamp() {
+        let mut st = Status::new(Some(8), Format::Text);
+        assert!(st.validate_node(1, 10, &id(1000, (1 << 16) | 10)).is_ok());
+        assert!(st.validate_node(1, 11, &id(1000, (1 << 16) | 11)).is_ok());
+    }
+
+    #[test]
+    fn validate_node_accepts_counter_reset_on_timestamp_increment() {
+        let mut st = Status::new(Some(8), Format::Text);
+        assert!(st.validate_node(1, 10, &id(1000, (1 << 16) | 10)).is_ok());
+        assert!(st.validate_node(1, 0, &id(1001, 1 << 16)).is_ok());
+    }
+
+    #[test]
+    fn validate_node_rejects_non_increasing_counter_within_a_timestamp() {
+        let mut st = Status::new(Some(8), Format::Text);
+        assert!(st.validate_node(1, 10, &id(1000, (1 << 16) | 10)).is_ok());
+        let err = st.validate_node(1, 10, &id(1000, (1 << 16) | 10));
+        assert!(matches!(err, Err((ErrorKind::CounterDidNotIncrease, _))));
+    }
+
+    #[test]
+    fn validate_node_rejects_clock_going_backwards() {
+        let mut st = Status::new(Some(8), Format::Text);
+        assert!(st.validate_node(1, 10, &id(1000, (1 << 16) | 10)).is_ok());
+        let err = st.validate_node(1, 0, &id(999, 1 << 16));
+        assert!(matches!(err, Err((ErrorKind::ClockWentBackwards, _))));
+    }
+
+    #[test]
+    fn validate_node_tracks_each_node_id_independently() {
+        let mut st = Status::new(Some(8), Format::Text);
+        assert!(st.validate_node(1, 10, &id(1000, (1 << 16) | 10)).is_ok());
+        // node 2 has never been seen, so the same counter is fine here.
+        assert!(st.validate_node(2, 10, &id(1000, (2 << 16) | 10)).is_ok());
+    }
+
+    #[test]
+    fn json_line_emits_null_interval_on_a_short_run_with_no_counter_update() {
+        let st = Status::new(None, Format::Json);
+        let line = st.json_line();
+        assert!(!line.contains("NaN"));
+        assert!(line.contains("\"mean_counter_update_interval_ms\":null"));
+    }
+
+    #[test]
+    fn json_line_emits_a_number_once_a_counter_update_was_observed() {
+        let mut st = Status::new(None, Format::Json);
+        st.n_counter_lo_update = 1;
+        st.sum_intervals_counter_update = 256;
+        let line = st.json_line();
+        assert!(line.contains("\"mean_counter_update_interval_ms\":256.000"));
+    }
+
+    #[test]
+    fn json_error_line_round_trips_the_reported_fields() {
+        let decoded = id(1000, 42);
+        let prev = id(900, 41);
+        let line = json_error_line(
+            ErrorKind::ClockWentBackwards,
+            b"000000000000",
+            Some(&decoded),
+            Some(&prev),
+            Some(7),
+        );
+        assert!(line.contains("\"error\":\"clock_went_backwards\""));
+        assert!(line.contains("\"node_ctr\":42"));
+        assert!(line.contains("\"node_id\":7"));
+    }
+}
+
 /// Holds representations and internal field values of a SCRU64 ID.
 #[derive(Clone, Eq, PartialEq, Hash, Debug, Default)]
 struct Identifier {